@@ -1,603 +1,2630 @@
-use std::collections::HashMap;
-use std::fmt;
-use std::fs::File;
-use std::hash::{Hash, Hasher};
-use std::io::prelude::*;
-use std::iter::Peekable;
-use std::ops::{Index, IndexMut};
-
-fn skip_whitespaces(json: &mut Peekable<std::str::Chars>) {
-    while let Some(&c) = json.peek() {
-        if c == ' ' || c == '\n' || c == '\t' {
-            json.next();
-        } else {
-            break;
-        }
-    }
-}
-
-#[derive(Debug, PartialEq, Clone)]
-pub enum Num {
-    Integer(i128),
-    Float(f64),
-}
-
-impl fmt::Display for Num {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Num::Integer(i) => write!(f, "{}", i),
-            Num::Float(fl) => write!(f, "{}", fl),
-        }
-    }
-}
-
-impl Eq for Num {
-    fn assert_receiver_is_total_eq(&self) {}
-}
-
-impl Hash for Num {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        match self {
-            Num::Integer(i) => i.hash(state),
-            Num::Float(fl) => fl.to_bits().hash(state),
-        }
-    }
-}
-
-#[derive(Debug, Eq, Hash, PartialEq, Clone)]
-pub enum JsonDtype {
-    String(String),
-    Number(Num),
-    Object(Json),
-    Array(Vec<JsonDtype>),
-    Boolean(bool),
-    Null,
-}
-
-#[allow(dead_code)]
-impl JsonDtype {
-    pub fn new<T>(value: T) -> Self
-    where
-        T: Into<JsonDtype>,
-    {
-        value.into()
-    }
-
-    pub fn stringify_pretty(&self, indent: usize, inc: usize) -> String {
-        match self {
-            JsonDtype::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
-            JsonDtype::Number(n) => format!("{}", n),
-            JsonDtype::Object(obj) => format!("{}", obj._stringify_pretty(indent, inc)),
-            JsonDtype::Array(arr) => {
-                if arr.len() == 0 {
-                    return "[]".to_string();
-                }
-                let mut s = String::from("[\n");
-                for (i, item) in arr.iter().enumerate() {
-                    if i > 0 {
-                        s.push_str(",\n");
-                    }
-                    s.push_str(&format!(
-                        "{:indent$}{}",
-                        "",
-                        item.stringify_pretty(indent + inc, inc),
-                        indent = indent + inc
-                    ))
-                }
-                s.push_str(&format!("\n{:indent$}]", "", indent = indent));
-                s
-            }
-            JsonDtype::Boolean(b) => format!("{}", b),
-            JsonDtype::Null => format!("null"),
-        }
-    }
-}
-
-impl From<String> for JsonDtype {
-    fn from(value: String) -> Self {
-        JsonDtype::String(value)
-    }
-}
-
-impl From<&str> for JsonDtype {
-    fn from(value: &str) -> Self {
-        JsonDtype::String(value.to_owned())
-    }
-}
-
-impl From<i128> for JsonDtype {
-    fn from(value: i128) -> Self {
-        JsonDtype::Number(Num::Integer(value))
-    }
-}
-
-impl From<f64> for JsonDtype {
-    fn from(value: f64) -> Self {
-        JsonDtype::Number(Num::Float(value))
-    }
-}
-
-impl From<Json> for JsonDtype {
-    fn from(value: Json) -> Self {
-        JsonDtype::Object(value)
-    }
-}
-
-impl From<Vec<JsonDtype>> for JsonDtype {
-    fn from(value: Vec<JsonDtype>) -> Self {
-        JsonDtype::Array(value)
-    }
-}
-
-impl From<bool> for JsonDtype {
-    fn from(value: bool) -> Self {
-        JsonDtype::Boolean(value)
-    }
-}
-
-impl From<()> for JsonDtype {
-    fn from(_: ()) -> Self {
-        JsonDtype::Null
-    }
-}
-
-impl fmt::Display for JsonDtype {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            JsonDtype::String(s) => {
-                write!(f, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
-            }
-            JsonDtype::Number(n) => write!(f, "{}", n),
-            JsonDtype::Object(obj) => write!(f, "{}", obj),
-            JsonDtype::Array(arr) => {
-                write!(f, "[")?;
-                for (i, item) in arr.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{}", item)?;
-                }
-                write!(f, "]")
-            }
-            JsonDtype::Boolean(b) => write!(f, "{}", b),
-            JsonDtype::Null => write!(f, "null"),
-        }
-    }
-}
-
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub struct Json {
-    map: HashMap<JsonDtype, JsonDtype>,
-}
-
-impl Hash for Json {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        for (key, value) in &self.map {
-            key.hash(state);
-            value.hash(state);
-        }
-    }
-}
-
-#[allow(dead_code)]
-impl Json {
-    pub fn new() -> Self {
-        Json {
-            map: HashMap::new(),
-        }
-    }
-
-    pub fn get<K>(&self, key: K) -> Option<&JsonDtype>
-    where
-        K: Into<JsonDtype>,
-    {
-        self.map.get(&key.into())
-    }
-
-    pub fn insert<K, V>(&mut self, key: K, value: V)
-    where
-        K: Into<JsonDtype>,
-        V: Into<JsonDtype>,
-    {
-        self.map.insert(key.into(), value.into());
-    }
-
-    pub fn remove<K>(&mut self, key: K)
-    where
-        K: Into<JsonDtype>,
-    {
-        self.map.remove(&key.into());
-    }
-
-    pub fn keys(&self) -> Vec<&JsonDtype> {
-        self.map.keys().collect()
-    }
-
-    pub fn stringify(&self) -> String {
-        let mut res = String::new();
-
-        res.push('{');
-        for (i, (key, value)) in self.iter().enumerate() {
-            if i > 0 {
-                res.push_str(", ");
-            }
-            match key {
-                JsonDtype::String(_) => res.push_str(format!("{}: {}", key, value).as_str()),
-                _ => res.push_str(format!("\"{}\": {}", key, value).as_str()),
-            }
-        }
-        res.push('}');
-        res
-    }
-
-    pub fn stringify_pretty(&self) -> String {
-        self._stringify_pretty(0, 4)
-    }
-
-    fn _stringify_pretty(&self, indent: usize, inc: usize) -> String {
-        
-        if self.is_empty() {
-            return "{}".to_string();
-        }
-
-        let mut res = String::new();
-
-        res.push_str(format!("{}\n", "{").as_str());
-
-        for (i, (key, value)) in self.iter().enumerate() {
-            if i > 0 {
-                res.push_str(",\n");
-            }
-            res.push_str(format!("{:indent$}", "", indent = indent + inc).as_str());
-            match key {
-                JsonDtype::String(_) => res.push_str(
-                    format!("{}: {}", key, value.stringify_pretty(indent + inc, inc)).as_str(),
-                ),
-                _ => res.push_str(
-                    format!("\"{}\": {}", key, value.stringify_pretty(indent + inc, inc)).as_str(),
-                ),
-            }
-        }
-
-        res.push_str(format!("\n{:indent$}{}", "", "}", indent = indent).as_str());
-        res
-    }
-
-    pub fn parse(json: &str) -> Json {
-        let mut json = json.chars().peekable();
-        skip_whitespaces(&mut json);
-
-        match json.peek() {
-            Some(&'{') => Json::parse_object(&mut json),
-            Some(&'[') => {
-                let mut res = Json::new();
-                res.insert("data", Json::parse_array(&mut json));
-                res
-            }
-            _ => {
-                panic!("{}", "unexpected char expected '{'")
-            }
-        }
-    }
-
-    fn parse_value(json: &mut Peekable<std::str::Chars>) -> JsonDtype {
-        skip_whitespaces(json);
-
-        match json.peek() {
-            Some(&'"') => Json::parse_string(json),
-            Some(&('0'..='9')) | Some(&'-') => Json::parse_number(json),
-            Some(&'t') | Some(&'f') => Json::parse_boolean(json),
-            Some(&'n') => Json::parse_null(json),
-            Some(&'[') => Json::parse_array(json),
-            Some(&'{') => Json::parse_object(json).into(),
-            _ => panic!(
-                "{} '{}'",
-                "expected '\"' or '0'..='9' or 't' or 'f' or 'n' or '[' or '{' found ",
-                json.peek().unwrap()
-            ),
-        }
-    }
-
-    fn parse_string(json: &mut Peekable<std::str::Chars>) -> JsonDtype {
-        let mut string = String::new();
-        json.next();
-        let mut skip: bool = false;
-        while let Some(&ch) = json.peek() {
-            match ch {
-                '\\' => {
-                    if skip {
-                        string.push(ch);
-                        skip = false;
-                        json.next();
-                        continue;
-                    }
-                    skip = true;
-                    json.next();
-                }
-                '"' => {
-                    if skip {
-                        string.push(ch);
-                        skip = false;
-                        json.next();
-                        continue;
-                    }
-                    json.next();
-                    return JsonDtype::String(string);
-                }
-                _ => {
-                    skip = false;
-                    string.push(ch);
-                    json.next();
-                }
-            }
-        }
-        panic!("unexpected char expected '\"' found 'EOF'");
-    }
-
-    fn parse_number(json: &mut Peekable<std::str::Chars>) -> JsonDtype {
-        let mut number = String::new();
-        let mut is_float = false;
-        let mut is_exp = false;
-
-        if json.peek().unwrap() == &'-' {
-            number.push('-');
-            json.next();
-        }
-
-        while let Some(&ch) = json.peek() {
-            match ch {
-                '.' => {
-                    number.push(ch);
-                    if is_float || is_exp {
-                        panic!("unexpected char found {} expected valid", number);
-                    }
-                    json.next();
-                    is_float = true;
-                }
-                'e' | 'E' => {
-                    if is_exp {
-                        panic!("unexpected char found {} expected valid", number);
-                    }
-                    is_exp = true;
-                    number.push(ch);
-                    json.next();
-                }
-                '0'..='9' => {
-                    number.push(ch);
-                    json.next();
-                }
-                _ => {
-                    if is_float {
-                        return JsonDtype::Number(Num::Float(number.parse::<f64>().unwrap()));
-                    }
-                    return JsonDtype::Number(Num::Integer(number.parse::<i128>().unwrap()));
-                }
-            }
-        }
-        panic!("unexpected char found 'EOF' expected '0'..='9'");
-    }
-
-    fn parse_boolean(json: &mut Peekable<std::str::Chars>) -> JsonDtype {
-        let mut boolean = String::new();
-        while let Some(&ch) = json.peek() {
-            match ch {
-                't' | 'r' | 'u' | 'e' | 'f' | 'a' | 'l' | 's' => {
-                    boolean.push(ch);
-                    json.next();
-                }
-                _ => {
-                    if boolean == "true" {
-                        return JsonDtype::Boolean(true);
-                    } else if boolean == "false" {
-                        return JsonDtype::Boolean(false);
-                    } else {
-                        panic!(
-                            "unexpected char found {} expected 'true' or 'false'",
-                            boolean
-                        );
-                    }
-                }
-            }
-        }
-        panic!("unexpected char found 'EOF' expected 'true' or 'false'");
-    }
-
-    fn parse_null(json: &mut Peekable<std::str::Chars>) -> JsonDtype {
-        let mut null = String::new();
-        while let Some(&ch) = json.peek() {
-            match ch {
-                'n' | 'u' | 'l' => {
-                    null.push(ch);
-                    json.next();
-                }
-                _ => {
-                    if null == "null" {
-                        return JsonDtype::Null;
-                    } else {
-                        panic!("unexpected char found {} expected 'null'", null);
-                    }
-                }
-            }
-        }
-        panic!("unexpected char found 'EOF' expected 'null'");
-    }
-
-    fn parse_array(json: &mut Peekable<std::str::Chars>) -> JsonDtype {
-        let mut array = Vec::new();
-        json.next();
-        while json.peek().is_some() {
-            skip_whitespaces(json);
-            match json.peek().unwrap() {
-                ']' => {
-                    json.next();
-                    return JsonDtype::Array(array);
-                }
-                ',' => {
-                    json.next();
-                }
-                _ => {
-                    array.push(Json::parse_value(json));
-                }
-            }
-        }
-        panic!("unexpected char expected ']' found 'EOF'");
-    }
-
-    fn parse_object(json: &mut Peekable<std::str::Chars>) -> Json {
-        let mut object = Json::new();
-        json.next();
-        while json.peek().is_some() {
-            skip_whitespaces(json);
-            if json.peek() == Some(&'}') {
-                json.next();
-                return object;
-            }
-
-            let key = Json::parse_value(json);
-
-            skip_whitespaces(json);
-            match json.peek() {
-                Some(&':') => {
-                    json.next();
-                }
-                _ => {
-                    panic!(
-                        "unexpected char expected ':' found {}",
-                        json.peek().unwrap()
-                    );
-                }
-            }
-
-            let value = Json::parse_value(json);
-            object.insert(key, value);
-
-            skip_whitespaces(json);
-            match json.peek() {
-                Some(&',') => {
-                    json.next();
-                }
-                Some(&'}') => {
-                    json.next();
-                    return object;
-                }
-                _ => {
-                    panic!(
-                        "unexpected char Expected ',' or '{}' found {}",
-                        "}",
-                        json.peek().unwrap()
-                    );
-                }
-            }
-        }
-        panic!("{}", "unexpected char expected '}'");
-    }
-
-    pub fn len(&self) -> usize {
-        self.map.len()
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.map.is_empty()
-    }
-
-    pub fn clear(&mut self) {
-        self.map.clear();
-    }
-
-    pub fn contains_key(&self, key: &JsonDtype) -> bool {
-        self.map.contains_key(key)
-    }
-
-    pub fn iter(&self) -> std::collections::hash_map::Iter<JsonDtype, JsonDtype> {
-        self.map.iter()
-    }
-
-    pub fn update(&mut self, other: Json) {
-        for (key, value) in other.iter() {
-            self.map.insert(key.clone(), value.clone());
-        }
-    }
-
-    pub fn load(file: &mut File) -> Json {
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).expect("read failed");
-        Json::parse(&contents)
-    }
-
-    pub fn dump(&self, file: &mut File) {
-        file.write(self.to_string().as_bytes())
-            .expect("write failed");
-    }
-
-    pub fn dumps(&self, file: &mut File) {
-        file.write(self.stringify().as_bytes())
-            .expect("write failed");
-    }
-
-    pub fn dumps_pretty(&self, file: &mut File) {
-        file.write(self.stringify_pretty().as_bytes())
-            .expect("write failed");
-    }
-}
-
-impl<K> Index<K> for Json
-where
-    K: Into<JsonDtype>,
-{
-    type Output = JsonDtype;
-
-    fn index(&self, index: K) -> &Self::Output {
-        &self.map.index(&index.into())
-    }
-}
-
-impl<K> IndexMut<K> for Json
-where
-    K: Into<JsonDtype>,
-{
-    fn index_mut(&mut self, index: K) -> &mut JsonDtype {
-        self.map.get_mut(&index.into()).unwrap()
-    }
-}
-
-impl fmt::Display for Json {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{{")?;
-        for (i, (key, value)) in self.iter().enumerate() {
-            if i > 0 {
-                write!(f, ", ")?;
-            }
-            write!(f, "{}: {}", key, value)?;
-        }
-        write!(f, "}}")
-    }
-}
-
-fn main() {
-    let mut json_obj = Json::parse(
-        r#"{"Hello": "World!", "potatoes": [1, 2, 3, { "a": 1 , "b": false, "c": null }],}"#,
-    );
-    println!("{}", json_obj);
-
-    json_obj.insert("age", 20);
-    println!("{}", json_obj.stringify_pretty());
-
-    println!("{}", json_obj.get("Hello").unwrap());
-
-    json_obj.remove("Hello");
-    println!("{}", json_obj);
-
-    let mut json_obj2 = Json::new();
-    json_obj2.insert("age", 21);
-
-    println!("{}", json_obj2["age"]);
-    json_obj2["age"] = 22.into();
-    println!("{}", json_obj2["age"]);
-
-    json_obj.update(json_obj2);
-    println!("{}", json_obj);
-
-
-    let mut data_file = File::create("data.json").expect("creation failed");
-    json_obj.dumps_pretty(&mut data_file);
-}
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::prelude::*;
+use std::iter::Peekable;
+use std::ops::{Index, IndexMut};
+
+/// A JSON parse error, reported at the line/column where it was detected.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    UnexpectedChar { found: char, expected: &'static str },
+    UnexpectedEof { expected: &'static str },
+    InvalidNumber(String),
+    InvalidEscape(String),
+    UnescapedControlCharacter(char),
+    TrailingGarbage,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::UnexpectedChar { found, expected } => write!(
+                f,
+                "line {}, column {}: unexpected char '{}', expected {}",
+                self.line, self.column, found, expected
+            ),
+            ErrorKind::UnexpectedEof { expected } => write!(
+                f,
+                "line {}, column {}: unexpected end of input, expected {}",
+                self.line, self.column, expected
+            ),
+            ErrorKind::InvalidNumber(s) => write!(
+                f,
+                "line {}, column {}: invalid number literal '{}'",
+                self.line, self.column, s
+            ),
+            ErrorKind::InvalidEscape(s) => write!(
+                f,
+                "line {}, column {}: invalid escape sequence '{}'",
+                self.line, self.column, s
+            ),
+            ErrorKind::UnescapedControlCharacter(ch) => write!(
+                f,
+                "line {}, column {}: control character U+{:04X} must be escaped in a string",
+                self.line, self.column, *ch as u32
+            ),
+            ErrorKind::TrailingGarbage => write!(
+                f,
+                "line {}, column {}: trailing characters after root value",
+                self.line, self.column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type ParseResult<T> = Result<T, ParseError>;
+
+/// Wraps any `char` iterator with the line/column position of the next char,
+/// so parse errors can be reported at the byte they occurred at. Generic
+/// over the source iterator so the same cursor backs both the in-memory
+/// `Json::parse` and the streaming [`Parser`].
+struct Cursor<I: Iterator<Item = char>> {
+    chars: Peekable<I>,
+    line: usize,
+    column: usize,
+}
+
+impl<I: Iterator<Item = char>> Cursor<I> {
+    fn new(chars: I) -> Self {
+        Cursor {
+            chars: chars.peekable(),
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next();
+        if let Some(c) = ch {
+            if c == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
+        }
+        ch
+    }
+
+    fn error(&self, kind: ErrorKind) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            kind,
+        }
+    }
+}
+
+fn skip_whitespaces<I: Iterator<Item = char>>(json: &mut Cursor<I>) {
+    while let Some(c) = json.peek() {
+        if c == ' ' || c == '\n' || c == '\t' || c == '\r' {
+            json.next();
+        } else {
+            break;
+        }
+    }
+}
+
+/// Decodes the character(s) following a `\` in a JSON string, including
+/// `\uXXXX` hex escapes and UTF-16 surrogate pairs.
+fn scan_escape<I: Iterator<Item = char>>(json: &mut Cursor<I>) -> ParseResult<char> {
+    match json.next() {
+        Some('"') => Ok('"'),
+        Some('\\') => Ok('\\'),
+        Some('/') => Ok('/'),
+        Some('n') => Ok('\n'),
+        Some('t') => Ok('\t'),
+        Some('r') => Ok('\r'),
+        Some('b') => Ok('\u{08}'),
+        Some('f') => Ok('\u{0C}'),
+        Some('u') => {
+            let hi = scan_hex4(json)?;
+            if (0xDC00..=0xDFFF).contains(&hi) {
+                return Err(json.error(ErrorKind::InvalidEscape(format!("\\u{:04x}", hi))));
+            }
+            if (0xD800..=0xDBFF).contains(&hi) {
+                if json.next() != Some('\\') || json.next() != Some('u') {
+                    return Err(json.error(ErrorKind::InvalidEscape(format!("\\u{:04x}", hi))));
+                }
+                let lo = scan_hex4(json)?;
+                if !(0xDC00..=0xDFFF).contains(&lo) {
+                    return Err(json.error(ErrorKind::InvalidEscape(format!(
+                        "\\u{:04x}\\u{:04x}",
+                        hi, lo
+                    ))));
+                }
+                let combined = 0x10000 + (hi - 0xD800) * 0x400 + (lo - 0xDC00);
+                return char::from_u32(combined).ok_or_else(|| {
+                    json.error(ErrorKind::InvalidEscape(format!(
+                        "\\u{:04x}\\u{:04x}",
+                        hi, lo
+                    )))
+                });
+            }
+            char::from_u32(hi)
+                .ok_or_else(|| json.error(ErrorKind::InvalidEscape(format!("\\u{:04x}", hi))))
+        }
+        Some(ch) => Err(json.error(ErrorKind::InvalidEscape(ch.to_string()))),
+        None => Err(json.error(ErrorKind::UnexpectedEof {
+            expected: "escape sequence",
+        })),
+    }
+}
+
+fn scan_hex4<I: Iterator<Item = char>>(json: &mut Cursor<I>) -> ParseResult<u32> {
+    let mut hex = String::with_capacity(4);
+    for _ in 0..4 {
+        match json.next() {
+            Some(ch) if ch.is_ascii_hexdigit() => hex.push(ch),
+            Some(ch) => {
+                return Err(json.error(ErrorKind::InvalidEscape(format!("\\u{}{}", hex, ch))))
+            }
+            None => {
+                return Err(json.error(ErrorKind::UnexpectedEof {
+                    expected: "4 hex digits",
+                }))
+            }
+        }
+    }
+    u32::from_str_radix(&hex, 16)
+        .map_err(|_| json.error(ErrorKind::InvalidEscape(format!("\\u{}", hex))))
+}
+
+fn scan_string_literal<I: Iterator<Item = char>>(json: &mut Cursor<I>) -> ParseResult<String> {
+    let mut string = String::new();
+    json.next();
+    loop {
+        match json.next() {
+            Some('"') => return Ok(string),
+            Some('\\') => string.push(scan_escape(json)?),
+            Some(ch) if (ch as u32) < 0x20 => {
+                return Err(json.error(ErrorKind::UnescapedControlCharacter(ch)))
+            }
+            Some(ch) => string.push(ch),
+            None => return Err(json.error(ErrorKind::UnexpectedEof { expected: "'\"'" })),
+        }
+    }
+}
+
+fn scan_number_literal<I: Iterator<Item = char>>(json: &mut Cursor<I>) -> ParseResult<Num> {
+    let mut number = String::new();
+    let mut is_float = false;
+    let mut is_exp = false;
+    let mut int_digits = 0usize;
+    let mut leading_zero = false;
+
+    if json.peek() == Some('-') {
+        number.push('-');
+        json.next();
+    }
+
+    loop {
+        match json.peek() {
+            Some(ch @ '.') => {
+                number.push(ch);
+                if is_float || is_exp {
+                    return Err(json.error(ErrorKind::InvalidNumber(number)));
+                }
+                json.next();
+                is_float = true;
+            }
+            Some(ch @ ('e' | 'E')) => {
+                if is_exp {
+                    return Err(json.error(ErrorKind::InvalidNumber(number)));
+                }
+                is_exp = true;
+                number.push(ch);
+                json.next();
+                // The exponent's sign is optional but, unlike the leading
+                // sign, can be `+` as well as `-`.
+                if let Some(sign @ ('+' | '-')) = json.peek() {
+                    number.push(sign);
+                    json.next();
+                }
+            }
+            Some(ch @ '0'..='9') => {
+                // The JSON spec allows a bare "0" integer part but forbids
+                // further digits after it (e.g. "0123"), so a second digit
+                // right after a leading zero is a hard error.
+                if !is_float && !is_exp {
+                    if leading_zero {
+                        return Err(json.error(ErrorKind::InvalidNumber(format!("{}{}", number, ch))));
+                    }
+                    if int_digits == 0 && ch == '0' {
+                        leading_zero = true;
+                    }
+                    int_digits += 1;
+                }
+                number.push(ch);
+                json.next();
+            }
+            _ => {
+                if number.is_empty() || number == "-" {
+                    return Err(json.error(ErrorKind::InvalidNumber(number)));
+                }
+                if is_float || is_exp {
+                    return number
+                        .parse::<f64>()
+                        .map(Num::Float)
+                        .map_err(|_| json.error(ErrorKind::InvalidNumber(number)));
+                }
+                // Prefer the unsigned representation for non-negative
+                // integers that fit in a u64, mirroring rustc-serialize's
+                // I64/U64/F64 split; anything wider falls back to i128.
+                if !number.starts_with('-') {
+                    if let Ok(u) = number.parse::<u64>() {
+                        return Ok(Num::Unsigned(u));
+                    }
+                }
+                return number
+                    .parse::<i128>()
+                    .map(Num::Integer)
+                    .map_err(|_| json.error(ErrorKind::InvalidNumber(number)));
+            }
+        }
+    }
+}
+
+fn scan_boolean_literal<I: Iterator<Item = char>>(json: &mut Cursor<I>) -> ParseResult<bool> {
+    let mut boolean = String::new();
+    while let Some(ch) = json.peek() {
+        match ch {
+            't' | 'r' | 'u' | 'e' | 'f' | 'a' | 'l' | 's' => {
+                boolean.push(ch);
+                json.next();
+            }
+            _ => break,
+        }
+    }
+    match boolean.as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(json.error(ErrorKind::UnexpectedChar {
+            found: boolean.chars().next().unwrap_or('\0'),
+            expected: "'true' or 'false'",
+        })),
+    }
+}
+
+fn scan_null_literal<I: Iterator<Item = char>>(json: &mut Cursor<I>) -> ParseResult<()> {
+    let mut null = String::new();
+    while let Some(ch) = json.peek() {
+        match ch {
+            'n' | 'u' | 'l' => {
+                null.push(ch);
+                json.next();
+            }
+            _ => break,
+        }
+    }
+    if null == "null" {
+        Ok(())
+    } else {
+        Err(json.error(ErrorKind::UnexpectedChar {
+            found: null.chars().next().unwrap_or('\0'),
+            expected: "'null'",
+        }))
+    }
+}
+
+/// Escapes a string for embedding between `"` in serialized JSON output:
+/// `\`, `"` and the standard two-character escapes get their short form,
+/// other control characters below 0x20 get a `\u00XX` escape.
+fn escape_json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0C}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A parsed JSON number. Non-negative integers that fit in a `u64` are kept
+/// as [`Num::Unsigned`] rather than widened straight to [`Num::Integer`]'s
+/// `i128`, matching the I64/U64/F64 split rustc-serialize's JSON number type
+/// uses. Integers wider than `u64` (including all negative ones) still use
+/// `Integer`; literals too large even for `i128` are rejected at parse time,
+/// since this crate has no arbitrary-precision integer to fall back to.
+#[derive(Debug, Clone)]
+pub enum Num {
+    Integer(i128),
+    Unsigned(u64),
+    Float(f64),
+}
+
+impl Num {
+    /// The value as an `i128` if it's an `Integer` or `Unsigned`, so the two
+    /// integer variants can be compared/hashed as the same number regardless
+    /// of which one a particular construction path happened to produce.
+    fn as_i128(&self) -> Option<i128> {
+        match self {
+            Num::Integer(i) => Some(*i),
+            Num::Unsigned(u) => Some(*u as i128),
+            Num::Float(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Num {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Num::Integer(i) => write!(f, "{}", i),
+            Num::Unsigned(u) => write!(f, "{}", u),
+            Num::Float(fl) => {
+                if fl.is_nan() || fl.is_infinite() {
+                    // Neither is valid JSON; emit `null` rather than produce
+                    // output that wouldn't reparse.
+                    return write!(f, "null");
+                }
+                let s = fl.to_string();
+                if s.contains('.') || s.contains('e') || s.contains('E') {
+                    write!(f, "{}", s)
+                } else {
+                    // `f64::to_string` drops the decimal point for whole
+                    // numbers (`2.0` -> "2"), which would reparse as an
+                    // integer. Always keep a `.0` so floats round-trip.
+                    write!(f, "{}.0", s)
+                }
+            }
+        }
+    }
+}
+
+impl PartialEq for Num {
+    fn eq(&self, other: &Self) -> bool {
+        // Integer and Unsigned are just two representations of the same
+        // number space (see scan_number_literal), so an Integer(20) parsed
+        // one way must equal an Unsigned(20) parsed another; only Float is
+        // kept distinct, matching JSON's own int/float type split.
+        if let (Some(a), Some(b)) = (self.as_i128(), other.as_i128()) {
+            return a == b;
+        }
+        match (self, other) {
+            (Num::Float(a), Num::Float(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Num {
+    fn assert_receiver_is_total_eq(&self) {}
+}
+
+impl Hash for Num {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.as_i128() {
+            Some(i) => i.hash(state),
+            None => match self {
+                Num::Float(fl) => fl.to_bits().hash(state),
+                Num::Integer(_) | Num::Unsigned(_) => unreachable!("covered by as_i128 above"),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Eq, Hash, PartialEq, Clone)]
+pub enum JsonDtype {
+    String(String),
+    Number(Num),
+    Object(Json),
+    Array(Vec<JsonDtype>),
+    Boolean(bool),
+    Null,
+}
+
+#[allow(dead_code)]
+impl JsonDtype {
+    pub fn new<T>(value: T) -> Self
+    where
+        T: Into<JsonDtype>,
+    {
+        value.into()
+    }
+
+    pub fn stringify_pretty(&self, indent: usize, inc: usize) -> String {
+        match self {
+            JsonDtype::String(s) => format!("\"{}\"", escape_json_string(s)),
+            JsonDtype::Number(n) => format!("{}", n),
+            JsonDtype::Object(obj) => format!("{}", obj._stringify_pretty(indent, inc)),
+            JsonDtype::Array(arr) => {
+                if arr.len() == 0 {
+                    return "[]".to_string();
+                }
+                let mut s = String::from("[\n");
+                for (i, item) in arr.iter().enumerate() {
+                    if i > 0 {
+                        s.push_str(",\n");
+                    }
+                    s.push_str(&format!(
+                        "{:indent$}{}",
+                        "",
+                        item.stringify_pretty(indent + inc, inc),
+                        indent = indent + inc
+                    ))
+                }
+                s.push_str(&format!("\n{:indent$}]", "", indent = indent));
+                s
+            }
+            JsonDtype::Boolean(b) => format!("{}", b),
+            JsonDtype::Null => format!("null"),
+        }
+    }
+}
+
+impl From<String> for JsonDtype {
+    fn from(value: String) -> Self {
+        JsonDtype::String(value)
+    }
+}
+
+impl From<&str> for JsonDtype {
+    fn from(value: &str) -> Self {
+        JsonDtype::String(value.to_owned())
+    }
+}
+
+impl From<i128> for JsonDtype {
+    fn from(value: i128) -> Self {
+        JsonDtype::Number(Num::Integer(value))
+    }
+}
+
+impl From<f64> for JsonDtype {
+    fn from(value: f64) -> Self {
+        JsonDtype::Number(Num::Float(value))
+    }
+}
+
+impl From<Json> for JsonDtype {
+    fn from(value: Json) -> Self {
+        JsonDtype::Object(value)
+    }
+}
+
+impl From<Vec<JsonDtype>> for JsonDtype {
+    fn from(value: Vec<JsonDtype>) -> Self {
+        JsonDtype::Array(value)
+    }
+}
+
+impl From<bool> for JsonDtype {
+    fn from(value: bool) -> Self {
+        JsonDtype::Boolean(value)
+    }
+}
+
+impl From<()> for JsonDtype {
+    fn from(_: ()) -> Self {
+        JsonDtype::Null
+    }
+}
+
+impl fmt::Display for JsonDtype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonDtype::String(s) => {
+                write!(f, "\"{}\"", escape_json_string(s))
+            }
+            JsonDtype::Number(n) => write!(f, "{}", n),
+            JsonDtype::Object(obj) => write!(f, "{}", obj),
+            JsonDtype::Array(arr) => {
+                write!(f, "[")?;
+                for (i, item) in arr.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            JsonDtype::Boolean(b) => write!(f, "{}", b),
+            JsonDtype::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// An insertion-ordered map from [`JsonDtype`] keys to [`JsonDtype`] values.
+///
+/// Object members need to come back out in the order they were inserted so
+/// `stringify`/`stringify_pretty` are stable and round-trippable; a plain
+/// `HashMap` only gives that for equality, not iteration order. Entries live
+/// in a `Vec` in insertion order, and `index` is a side table from key to
+/// slot giving `O(1)` lookup.
+#[derive(Debug, Clone)]
+struct OrderedMap {
+    entries: Vec<(JsonDtype, JsonDtype)>,
+    index: HashMap<JsonDtype, usize>,
+}
+
+impl OrderedMap {
+    fn new() -> Self {
+        OrderedMap {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    fn get(&self, key: &JsonDtype) -> Option<&JsonDtype> {
+        let i = self.index.get(key).copied()?;
+        Some(&self.entries[i].1)
+    }
+
+    fn get_mut(&mut self, key: &JsonDtype) -> Option<&mut JsonDtype> {
+        let i = self.index.get(key).copied()?;
+        Some(&mut self.entries[i].1)
+    }
+
+    fn insert(&mut self, key: JsonDtype, value: JsonDtype) {
+        match self.index.get(&key).copied() {
+            Some(i) => self.entries[i].1 = value,
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &JsonDtype) {
+        if let Some(i) = self.index.remove(key) {
+            self.entries.remove(i);
+            for slot in self.index.values_mut() {
+                if *slot > i {
+                    *slot -= 1;
+                }
+            }
+        }
+    }
+
+    fn keys(&self) -> Vec<&JsonDtype> {
+        self.entries.iter().map(|(k, _)| k).collect()
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    fn contains_key(&self, key: &JsonDtype) -> bool {
+        self.index.contains_key(key)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&JsonDtype, &JsonDtype)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+}
+
+impl PartialEq for OrderedMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries.len() == other.entries.len()
+            && self.entries.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl Eq for OrderedMap {}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Json {
+    map: OrderedMap,
+}
+
+impl Hash for Json {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Entries are insertion-ordered for stable iteration, but two maps
+        // with the same members in a different insertion order still compare
+        // equal, so their hashes must match too. XOR-combine each pair's
+        // hash so the result doesn't depend on iteration order.
+        let mut combined: u64 = 0;
+        for (key, value) in self.iter() {
+            let mut pair_hasher = std::collections::hash_map::DefaultHasher::new();
+            key.hash(&mut pair_hasher);
+            value.hash(&mut pair_hasher);
+            combined ^= pair_hasher.finish();
+        }
+        combined.hash(state);
+    }
+}
+
+#[allow(dead_code)]
+impl Json {
+    pub fn new() -> Self {
+        Json {
+            map: OrderedMap::new(),
+        }
+    }
+
+    pub fn get<K>(&self, key: K) -> Option<&JsonDtype>
+    where
+        K: Into<JsonDtype>,
+    {
+        self.map.get(&key.into())
+    }
+
+    pub fn insert<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<JsonDtype>,
+        V: Into<JsonDtype>,
+    {
+        self.map.insert(key.into(), value.into());
+    }
+
+    pub fn remove<K>(&mut self, key: K)
+    where
+        K: Into<JsonDtype>,
+    {
+        self.map.remove(&key.into());
+    }
+
+    pub fn keys(&self) -> Vec<&JsonDtype> {
+        self.map.keys()
+    }
+
+    pub fn stringify(&self) -> String {
+        let mut res = String::new();
+
+        res.push('{');
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                res.push_str(", ");
+            }
+            match key {
+                JsonDtype::String(_) => res.push_str(format!("{}: {}", key, value).as_str()),
+                _ => res.push_str(format!("\"{}\": {}", key, value).as_str()),
+            }
+        }
+        res.push('}');
+        res
+    }
+
+    pub fn stringify_pretty(&self) -> String {
+        self._stringify_pretty(0, 4)
+    }
+
+    fn _stringify_pretty(&self, indent: usize, inc: usize) -> String {
+        
+        if self.is_empty() {
+            return "{}".to_string();
+        }
+
+        let mut res = String::new();
+
+        res.push_str(format!("{}\n", "{").as_str());
+
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                res.push_str(",\n");
+            }
+            res.push_str(format!("{:indent$}", "", indent = indent + inc).as_str());
+            match key {
+                JsonDtype::String(_) => res.push_str(
+                    format!("{}: {}", key, value.stringify_pretty(indent + inc, inc)).as_str(),
+                ),
+                _ => res.push_str(
+                    format!("\"{}\": {}", key, value.stringify_pretty(indent + inc, inc)).as_str(),
+                ),
+            }
+        }
+
+        res.push_str(format!("\n{:indent$}{}", "", "}", indent = indent).as_str());
+        res
+    }
+
+    pub fn parse(json: &str) -> ParseResult<Json> {
+        Builder::new(Parser::new(json.chars())).build()
+    }
+
+    /// Parses `json`, panicking on malformed input instead of returning a
+    /// [`ParseError`]. Kept for callers that prefer the old panicking API.
+    pub fn parse_unwrap(json: &str) -> Json {
+        Json::parse(json).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    pub fn contains_key(&self, key: &JsonDtype) -> bool {
+        self.map.contains_key(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&JsonDtype, &JsonDtype)> {
+        self.map.iter()
+    }
+
+    pub fn update(&mut self, other: Json) {
+        for (key, value) in other.iter() {
+            self.map.insert(key.clone(), value.clone());
+        }
+    }
+
+    pub fn load(file: &mut File) -> ParseResult<Json> {
+        let mut contents = String::new();
+        file.read_to_string(&mut contents).expect("read failed");
+        Json::parse(&contents)
+    }
+
+    pub fn dump(&self, file: &mut File) {
+        file.write(self.to_string().as_bytes())
+            .expect("write failed");
+    }
+
+    pub fn dumps(&self, file: &mut File) {
+        file.write(self.stringify().as_bytes())
+            .expect("write failed");
+    }
+
+    pub fn dumps_pretty(&self, file: &mut File) {
+        file.write(self.stringify_pretty().as_bytes())
+            .expect("write failed");
+    }
+
+    /// Evaluates a JSONPath expression against this document and returns
+    /// borrowed references to every matching value. Returns a
+    /// [`JsonPathError`] if `path` itself is malformed, rather than
+    /// panicking, since `path` is commonly attacker/user-supplied.
+    ///
+    /// A bare `$` resolves to the whole document, not a single member
+    /// value, and this crate has no `&JsonDtype` that can borrow a `Json`
+    /// (only the reverse), so that case can't be expressed here. It's
+    /// reported as a [`JsonPathError`] instead of silently returning an
+    /// empty match set; use [`Json::query_owned`], which can clone the
+    /// document into a `JsonDtype::Object`, if you need the root itself.
+    pub fn query(&self, path: &str) -> Result<Vec<&JsonDtype>, JsonPathError> {
+        let selectors = jsonpath::parse_path(path)?;
+        let nodes = jsonpath::eval_path(self, &selectors);
+        if nodes.iter().any(|n| matches!(n, jsonpath::NodeCtx::Root(_))) {
+            return Err(JsonPathError(format!(
+                "path {:?} resolves to the document root, which `query` cannot borrow as a \
+                 `&JsonDtype`; use `query_owned` instead",
+                path
+            )));
+        }
+        Ok(nodes
+            .into_iter()
+            .filter_map(|node| match node {
+                jsonpath::NodeCtx::Value(v) => Some(v),
+                jsonpath::NodeCtx::Root(_) => None,
+            })
+            .collect())
+    }
+
+    /// Like [`Json::query`], but clones the matches so the result can
+    /// outlive `self` — and, unlike `query`, can represent a bare `$`
+    /// query by cloning the whole document into a `JsonDtype::Object`.
+    pub fn query_owned(&self, path: &str) -> Result<Vec<JsonDtype>, JsonPathError> {
+        let selectors = jsonpath::parse_path(path)?;
+        Ok(jsonpath::eval_path(self, &selectors)
+            .into_iter()
+            .map(|node| match node {
+                jsonpath::NodeCtx::Value(v) => v.clone(),
+                jsonpath::NodeCtx::Root(json) => JsonDtype::Object(json.clone()),
+            })
+            .collect())
+    }
+}
+
+/// A single token in a streamed JSON document, emitted by [`Parser`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonEvent {
+    ObjectStart,
+    ObjectEnd,
+    ArrayStart,
+    ArrayEnd,
+    Key(String),
+    String(String),
+    Integer(i128),
+    Unsigned(u64),
+    Float(f64),
+    Boolean(bool),
+    Null,
+}
+
+/// One segment of a [`Parser`]'s current path through the document, mirroring
+/// rustc-serialize's `StackElement`: an array frame reports the index of the
+/// element currently being read, an object frame reports the key whose value
+/// is currently being read (or the last key read, before the next one
+/// arrives).
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackElement {
+    Index(u32),
+    Key(String),
+}
+
+/// The kind of container a [`Parser`]'s stack frame is inside. Unlike
+/// [`StackElement`], this only distinguishes container shape, not position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ContainerKind {
+    Object,
+    Array,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FrameState {
+    /// Start of the container, or right after a comma: a value (array) or a
+    /// key (object) is expected, as is the closing bracket (trailing commas
+    /// are tolerated, matching this crate's historical leniency).
+    ValueOrEnd,
+    /// An object key was just read and its `:` consumed; a value must follow.
+    AwaitingValue,
+    /// A value was just read; only `,` or the closing bracket may follow.
+    CommaOrEnd,
+}
+
+struct Frame {
+    kind: ContainerKind,
+    state: FrameState,
+    /// The current path segment for this frame; see [`StackElement`].
+    segment: StackElement,
+}
+
+/// A non-recursive pull parser: each [`Iterator::next`] call advances past
+/// exactly one token and returns the [`JsonEvent`] it produced, so callers
+/// can process arbitrarily large documents without materializing a tree.
+///
+/// Nesting is tracked with an explicit stack of frames instead of recursive
+/// calls, so parsing depth is bounded only by memory. Each frame also carries
+/// a [`StackElement`], so [`Parser::stack`] can report the current path
+/// through the document.
+pub struct Parser<I: Iterator<Item = char>> {
+    cursor: Cursor<I>,
+    stack: Vec<Frame>,
+    root_done: bool,
+}
+
+impl<I: Iterator<Item = char>> Parser<I> {
+    pub fn new(chars: I) -> Self {
+        Parser {
+            cursor: Cursor::new(chars),
+            stack: Vec::new(),
+            root_done: false,
+        }
+    }
+
+    /// The parser's current path through the document, outermost frame
+    /// first: an array frame is the index of the element being read, an
+    /// object frame is the most recently read key.
+    pub fn stack(&self) -> Vec<StackElement> {
+        self.stack.iter().map(|frame| frame.segment.clone()).collect()
+    }
+
+    fn close_container(&mut self) {
+        match self.stack.last_mut() {
+            // The `,` handler bumps the array index for the next element;
+            // don't also bump it here, or an array of containers double-
+            // counts every element that is itself an object/array.
+            Some(parent) => parent.state = FrameState::CommaOrEnd,
+            None => self.root_done = true,
+        }
+    }
+
+    fn read_scalar_event(&mut self) -> ParseResult<JsonEvent> {
+        match self.cursor.peek() {
+            Some('"') => scan_string_literal(&mut self.cursor).map(JsonEvent::String),
+            Some('0'..='9') | Some('-') => {
+                scan_number_literal(&mut self.cursor).map(|n| match n {
+                    Num::Integer(i) => JsonEvent::Integer(i),
+                    Num::Unsigned(u) => JsonEvent::Unsigned(u),
+                    Num::Float(f) => JsonEvent::Float(f),
+                })
+            }
+            Some('t') | Some('f') => scan_boolean_literal(&mut self.cursor).map(JsonEvent::Boolean),
+            Some('n') => scan_null_literal(&mut self.cursor).map(|()| JsonEvent::Null),
+            Some(found) => Err(self.cursor.error(ErrorKind::UnexpectedChar {
+                found,
+                expected: "a JSON value",
+            })),
+            None => Err(self.cursor.error(ErrorKind::UnexpectedEof {
+                expected: "a JSON value",
+            })),
+        }
+    }
+
+    fn read_root_value(&mut self) -> ParseResult<JsonEvent> {
+        match self.cursor.peek() {
+            Some('{') => {
+                self.cursor.next();
+                self.stack.push(Frame {
+                    kind: ContainerKind::Object,
+                    state: FrameState::ValueOrEnd,
+                    segment: StackElement::Key(String::new()),
+                });
+                Ok(JsonEvent::ObjectStart)
+            }
+            Some('[') => {
+                self.cursor.next();
+                self.stack.push(Frame {
+                    kind: ContainerKind::Array,
+                    state: FrameState::ValueOrEnd,
+                    segment: StackElement::Index(0),
+                });
+                Ok(JsonEvent::ArrayStart)
+            }
+            _ => {
+                let event = self.read_scalar_event()?;
+                self.root_done = true;
+                Ok(event)
+            }
+        }
+    }
+
+    fn read_value_event(&mut self) -> ParseResult<JsonEvent> {
+        match self.cursor.peek() {
+            Some('{') => {
+                self.cursor.next();
+                self.stack.push(Frame {
+                    kind: ContainerKind::Object,
+                    state: FrameState::ValueOrEnd,
+                    segment: StackElement::Key(String::new()),
+                });
+                Ok(JsonEvent::ObjectStart)
+            }
+            Some('[') => {
+                self.cursor.next();
+                self.stack.push(Frame {
+                    kind: ContainerKind::Array,
+                    state: FrameState::ValueOrEnd,
+                    segment: StackElement::Index(0),
+                });
+                Ok(JsonEvent::ArrayStart)
+            }
+            _ => {
+                let event = self.read_scalar_event()?;
+                self.stack.last_mut().unwrap().state = FrameState::CommaOrEnd;
+                Ok(event)
+            }
+        }
+    }
+
+    fn read_key_event(&mut self) -> ParseResult<JsonEvent> {
+        let key = scan_string_literal(&mut self.cursor)?;
+        skip_whitespaces(&mut self.cursor);
+        match self.cursor.next() {
+            Some(':') => {}
+            Some(found) => {
+                return Err(self
+                    .cursor
+                    .error(ErrorKind::UnexpectedChar { found, expected: "':'" }))
+            }
+            None => return Err(self.cursor.error(ErrorKind::UnexpectedEof { expected: "':'" })),
+        }
+        let frame = self.stack.last_mut().unwrap();
+        frame.state = FrameState::AwaitingValue;
+        frame.segment = StackElement::Key(key.clone());
+        Ok(JsonEvent::Key(key))
+    }
+}
+
+impl<I: Iterator<Item = char>> Iterator for Parser<I> {
+    type Item = ParseResult<JsonEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.stack.is_empty() {
+                skip_whitespaces(&mut self.cursor);
+                if self.root_done {
+                    return match self.cursor.peek() {
+                        None => None,
+                        Some(_) => Some(Err(self.cursor.error(ErrorKind::TrailingGarbage))),
+                    };
+                }
+                return Some(self.read_root_value());
+            }
+
+            let kind = self.stack.last().unwrap().kind;
+            let state = self.stack.last().unwrap().state;
+            skip_whitespaces(&mut self.cursor);
+
+            match (kind, state) {
+                (ContainerKind::Array, FrameState::ValueOrEnd) => match self.cursor.peek() {
+                    Some(']') => {
+                        self.cursor.next();
+                        self.stack.pop();
+                        self.close_container();
+                        return Some(Ok(JsonEvent::ArrayEnd));
+                    }
+                    None => {
+                        return Some(Err(
+                            self.cursor.error(ErrorKind::UnexpectedEof { expected: "']'" })
+                        ))
+                    }
+                    _ => return Some(self.read_value_event()),
+                },
+                (ContainerKind::Array, FrameState::CommaOrEnd) => match self.cursor.peek() {
+                    Some(']') => {
+                        self.cursor.next();
+                        self.stack.pop();
+                        self.close_container();
+                        return Some(Ok(JsonEvent::ArrayEnd));
+                    }
+                    Some(',') => {
+                        self.cursor.next();
+                        let frame = self.stack.last_mut().unwrap();
+                        frame.state = FrameState::ValueOrEnd;
+                        if let StackElement::Index(i) = frame.segment {
+                            frame.segment = StackElement::Index(i + 1);
+                        }
+                        continue;
+                    }
+                    None => {
+                        return Some(Err(self.cursor.error(ErrorKind::UnexpectedEof {
+                            expected: "',' or ']'",
+                        })))
+                    }
+                    Some(found) => {
+                        return Some(Err(self.cursor.error(ErrorKind::UnexpectedChar {
+                            found,
+                            expected: "',' or ']'",
+                        })))
+                    }
+                },
+                (ContainerKind::Array, FrameState::AwaitingValue) => {
+                    unreachable!("arrays never await a keyed value")
+                }
+                (ContainerKind::Object, FrameState::ValueOrEnd) => match self.cursor.peek() {
+                    Some('}') => {
+                        self.cursor.next();
+                        self.stack.pop();
+                        self.close_container();
+                        return Some(Ok(JsonEvent::ObjectEnd));
+                    }
+                    Some('"') => return Some(self.read_key_event()),
+                    None => {
+                        return Some(Err(self.cursor.error(ErrorKind::UnexpectedEof {
+                            expected: "a key or '}'",
+                        })))
+                    }
+                    Some(found) => {
+                        return Some(Err(self.cursor.error(ErrorKind::UnexpectedChar {
+                            found,
+                            expected: "a key or '}'",
+                        })))
+                    }
+                },
+                (ContainerKind::Object, FrameState::AwaitingValue) => {
+                    return Some(self.read_value_event())
+                }
+                (ContainerKind::Object, FrameState::CommaOrEnd) => match self.cursor.peek() {
+                    Some('}') => {
+                        self.cursor.next();
+                        self.stack.pop();
+                        self.close_container();
+                        return Some(Ok(JsonEvent::ObjectEnd));
+                    }
+                    Some(',') => {
+                        self.cursor.next();
+                        self.stack.last_mut().unwrap().state = FrameState::ValueOrEnd;
+                        continue;
+                    }
+                    None => {
+                        return Some(Err(self.cursor.error(ErrorKind::UnexpectedEof {
+                            expected: "',' or '}'",
+                        })))
+                    }
+                    Some(found) => {
+                        return Some(Err(self.cursor.error(ErrorKind::UnexpectedChar {
+                            found,
+                            expected: "',' or '}'",
+                        })))
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// A container still being filled in while [`Builder`] folds the event
+/// stream, one per nesting level. Kept on an explicit `Vec` (mirroring
+/// [`Parser`]'s own `Frame` stack) instead of on the Rust call stack, so
+/// `Builder::build` stays non-recursive at arbitrary nesting depth.
+enum BuilderFrame {
+    Object { object: Json, key: Option<String> },
+    Array(Vec<JsonDtype>),
+}
+
+impl BuilderFrame {
+    /// Attaches a just-completed value to this frame: pushed for an array,
+    /// or inserted under the most recently read key for an object.
+    fn attach(&mut self, value: JsonDtype) {
+        match self {
+            BuilderFrame::Object { object, key } => {
+                let key = key
+                    .take()
+                    .expect("parser only emits a value inside an object after a Key event");
+                object.insert(key, value);
+            }
+            BuilderFrame::Array(array) => array.push(value),
+        }
+    }
+}
+
+/// Folds a [`Parser`]'s event stream back into a [`Json`] tree, so
+/// [`Json::parse`] is just a `Builder` driving a `Parser` to completion.
+pub struct Builder<I: Iterator<Item = char>> {
+    events: Peekable<Parser<I>>,
+}
+
+impl<I: Iterator<Item = char>> Builder<I> {
+    pub fn new(parser: Parser<I>) -> Self {
+        Builder {
+            events: parser.peekable(),
+        }
+    }
+
+    pub fn build(mut self) -> ParseResult<Json> {
+        let mut stack: Vec<BuilderFrame> = Vec::new();
+
+        // A bare top-level array has nowhere to live in a `Json` (which is
+        // always an object), so — matching this crate's historical
+        // behavior — it's wrapped as `{"data": [...]}` rather than
+        // rejected.
+        match self.events.next() {
+            Some(Ok(JsonEvent::ObjectStart)) => {
+                stack.push(BuilderFrame::Object {
+                    object: Json::new(),
+                    key: None,
+                });
+            }
+            Some(Ok(JsonEvent::ArrayStart)) => {
+                stack.push(BuilderFrame::Array(Vec::new()));
+            }
+            Some(Ok(other)) => {
+                return Err(ParseError {
+                    line: 1,
+                    column: 1,
+                    kind: ErrorKind::UnexpectedChar {
+                        found: event_char_hint(&other),
+                        expected: "'{' or '['",
+                    },
+                })
+            }
+            Some(Err(e)) => return Err(e),
+            None => {
+                return Err(ParseError {
+                    line: 1,
+                    column: 1,
+                    kind: ErrorKind::UnexpectedEof {
+                        expected: "'{' or '['",
+                    },
+                })
+            }
+        }
+
+        // Fold every remaining event onto `stack` iteratively; the root
+        // value closes once `stack` empties back out, however deeply
+        // nested the document was.
+        let root = loop {
+            let event = match self.events.next() {
+                Some(Ok(e)) => e,
+                Some(Err(e)) => return Err(e),
+                None => unreachable!("parser keeps yielding events until the root value closes"),
+            };
+
+            match event {
+                JsonEvent::ObjectStart => stack.push(BuilderFrame::Object {
+                    object: Json::new(),
+                    key: None,
+                }),
+                JsonEvent::ArrayStart => stack.push(BuilderFrame::Array(Vec::new())),
+                JsonEvent::Key(key) => match stack.last_mut() {
+                    Some(BuilderFrame::Object { key: pending, .. }) => *pending = Some(key),
+                    _ => unreachable!("parser only emits Key while inside an object"),
+                },
+                JsonEvent::ObjectEnd => {
+                    let object = match stack.pop() {
+                        Some(BuilderFrame::Object { object, .. }) => object,
+                        _ => unreachable!("parser only emits ObjectEnd to close an object frame"),
+                    };
+                    match stack.last_mut() {
+                        None => break object,
+                        Some(parent) => parent.attach(JsonDtype::Object(object)),
+                    }
+                }
+                JsonEvent::ArrayEnd => {
+                    let array = match stack.pop() {
+                        Some(BuilderFrame::Array(array)) => array,
+                        _ => unreachable!("parser only emits ArrayEnd to close an array frame"),
+                    };
+                    match stack.last_mut() {
+                        // Only the root array can close with nothing left
+                        // on the stack; wrap it the way this crate always
+                        // has, since `Json` can't represent a bare array.
+                        None => {
+                            let mut wrapped = Json::new();
+                            wrapped.insert("data", JsonDtype::Array(array));
+                            break wrapped;
+                        }
+                        Some(parent) => parent.attach(JsonDtype::Array(array)),
+                    }
+                }
+                other => {
+                    let value = Builder::<I>::scalar(other);
+                    stack
+                        .last_mut()
+                        .expect("scalars only appear nested inside a container")
+                        .attach(value);
+                }
+            }
+        };
+
+        // The parser only reports trailing garbage once asked for the event
+        // after the root value closes, so poll it once more here.
+        match self.events.next() {
+            None => Ok(root),
+            Some(Err(e)) => Err(e),
+            Some(Ok(_)) => unreachable!("parser only yields one root-level value"),
+        }
+    }
+
+    fn scalar(event: JsonEvent) -> JsonDtype {
+        match event {
+            JsonEvent::String(s) => JsonDtype::String(s),
+            JsonEvent::Integer(i) => JsonDtype::Number(Num::Integer(i)),
+            JsonEvent::Unsigned(u) => JsonDtype::Number(Num::Unsigned(u)),
+            JsonEvent::Float(f) => JsonDtype::Number(Num::Float(f)),
+            JsonEvent::Boolean(b) => JsonDtype::Boolean(b),
+            JsonEvent::Null => JsonDtype::Null,
+            JsonEvent::Key(_)
+            | JsonEvent::ObjectStart
+            | JsonEvent::ObjectEnd
+            | JsonEvent::ArrayStart
+            | JsonEvent::ArrayEnd => {
+                unreachable!("container and key events are handled by their own match arms")
+            }
+        }
+    }
+}
+
+fn event_char_hint(event: &JsonEvent) -> char {
+    match event {
+        JsonEvent::String(_) => '"',
+        JsonEvent::Integer(_) | JsonEvent::Unsigned(_) | JsonEvent::Float(_) => '0',
+        JsonEvent::Boolean(_) => 't',
+        JsonEvent::Null => 'n',
+        JsonEvent::Key(_) | JsonEvent::ObjectStart | JsonEvent::ObjectEnd
+        | JsonEvent::ArrayStart | JsonEvent::ArrayEnd => '?',
+    }
+}
+
+/// An error produced when a JSONPath expression string itself is malformed
+/// (as opposed to evaluation simply finding no matches, which is not an
+/// error and just yields an empty result).
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPathError(String);
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid jsonpath expression: {}", self.0)
+    }
+}
+
+impl std::error::Error for JsonPathError {}
+
+/// JSONPath tokenizing, parsing and evaluation.
+///
+/// Supports `$`, `.name`, `['name']`, `[n]`, `*`, `..`, `[start:end:step]`,
+/// `[a,b]` unions and `[?(@.field <op> literal)]` filters.
+mod jsonpath {
+    use super::{Json, JsonDtype, JsonPathError, Num};
+    use std::iter::Peekable;
+    use std::str::Chars;
+
+    #[derive(Debug, Clone, Copy)]
+    pub enum NodeCtx<'a> {
+        Root(&'a Json),
+        Value(&'a JsonDtype),
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub(super) enum CmpOp {
+        Eq,
+        Ne,
+        Lt,
+        Le,
+        Gt,
+        Ge,
+    }
+
+    #[derive(Debug, Clone)]
+    enum PathSeg {
+        Name(String),
+        Index(i64),
+    }
+
+    #[derive(Debug, Clone)]
+    pub(super) struct FilterExpr {
+        path: Vec<PathSeg>,
+        op: CmpOp,
+        literal: JsonDtype,
+    }
+
+    #[derive(Debug, Clone)]
+    pub(super) enum Selector {
+        Child(String),
+        Wildcard,
+        RecursiveDescent,
+        Index(i64),
+        Slice(Option<i64>, Option<i64>, Option<i64>),
+        Union(Vec<Selector>),
+        Filter(FilterExpr),
+    }
+
+    fn is_ident_char(c: char) -> bool {
+        c.is_alphanumeric() || c == '_' || c == '-'
+    }
+
+    fn read_ident(chars: &mut Peekable<Chars>) -> Result<String, JsonPathError> {
+        let mut ident = String::new();
+        while let Some(&c) = chars.peek() {
+            if is_ident_char(c) {
+                ident.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        if ident.is_empty() {
+            return Err(JsonPathError("expected a name in jsonpath expression".to_string()));
+        }
+        Ok(ident)
+    }
+
+    fn read_bracket_content(chars: &mut Peekable<Chars>) -> Result<String, JsonPathError> {
+        let mut content = String::new();
+        let mut in_quotes = false;
+        let mut quote_char = '\'';
+        let mut depth = 0usize;
+        while let Some(&c) = chars.peek() {
+            match c {
+                '\'' | '"' if in_quotes && c == quote_char => {
+                    in_quotes = false;
+                    content.push(c);
+                    chars.next();
+                }
+                '\'' | '"' if !in_quotes => {
+                    in_quotes = true;
+                    quote_char = c;
+                    content.push(c);
+                    chars.next();
+                }
+                '[' if !in_quotes => {
+                    depth += 1;
+                    content.push(c);
+                    chars.next();
+                }
+                ']' if !in_quotes && depth > 0 => {
+                    depth -= 1;
+                    content.push(c);
+                    chars.next();
+                }
+                ']' if !in_quotes => {
+                    chars.next();
+                    return Ok(content);
+                }
+                _ => {
+                    content.push(c);
+                    chars.next();
+                }
+            }
+        }
+        Err(JsonPathError("unterminated '[' in jsonpath expression".to_string()))
+    }
+
+    fn parse_literal(raw: &str) -> Result<JsonDtype, JsonPathError> {
+        let raw = raw.trim();
+        if raw == "true" {
+            Ok(JsonDtype::Boolean(true))
+        } else if raw == "false" {
+            Ok(JsonDtype::Boolean(false))
+        } else if raw == "null" {
+            Ok(JsonDtype::Null)
+        } else if (raw.starts_with('"') && raw.ends_with('"') && raw.len() >= 2)
+            || (raw.starts_with('\'') && raw.ends_with('\'') && raw.len() >= 2)
+        {
+            Ok(JsonDtype::String(raw[1..raw.len() - 1].to_string()))
+        } else if raw.contains('.') {
+            raw.parse::<f64>()
+                .map(|f| JsonDtype::Number(Num::Float(f)))
+                .map_err(|_| JsonPathError(format!("invalid numeric literal '{}' in jsonpath filter", raw)))
+        } else {
+            raw.parse::<i128>()
+                .map(|i| JsonDtype::Number(Num::Integer(i)))
+                .map_err(|_| JsonPathError(format!("invalid numeric literal '{}' in jsonpath filter", raw)))
+        }
+    }
+
+    fn parse_relative_path(raw: &str) -> Result<Vec<PathSeg>, JsonPathError> {
+        let raw = raw.trim().trim_start_matches('@');
+        let mut segs = Vec::new();
+        for part in raw.split('.') {
+            if part.is_empty() {
+                continue;
+            }
+            let mut rest = part;
+            match rest.find('[') {
+                Some(bracket_pos) => {
+                    let name = &rest[..bracket_pos];
+                    if !name.is_empty() {
+                        segs.push(PathSeg::Name(name.to_string()));
+                    }
+                    rest = &rest[bracket_pos..];
+                    while let Some(stripped) = rest.strip_prefix('[') {
+                        let end = stripped.find(']').ok_or_else(|| {
+                            JsonPathError(format!("unterminated '[' in filter path '{}'", part))
+                        })?;
+                        let idx_raw = stripped[..end].trim_matches(|ch| ch == '\'' || ch == '"');
+                        let idx = idx_raw.parse::<i64>().map_err(|_| {
+                            JsonPathError(format!("invalid index '{}' in filter path", idx_raw))
+                        })?;
+                        segs.push(PathSeg::Index(idx));
+                        rest = &stripped[end + 1..];
+                    }
+                }
+                None => segs.push(PathSeg::Name(rest.to_string())),
+            }
+        }
+        Ok(segs)
+    }
+
+    fn parse_filter(raw: &str) -> Result<FilterExpr, JsonPathError> {
+        let raw = raw.trim();
+        let raw = raw
+            .strip_prefix("?(")
+            .and_then(|r| r.strip_suffix(')'))
+            .ok_or_else(|| JsonPathError(format!("malformed filter expression '{}'", raw)))?;
+
+        const OPS: [(&str, CmpOp); 6] = [
+            ("==", CmpOp::Eq),
+            ("!=", CmpOp::Ne),
+            ("<=", CmpOp::Le),
+            (">=", CmpOp::Ge),
+            ("<", CmpOp::Lt),
+            (">", CmpOp::Gt),
+        ];
+
+        for (token, op) in OPS {
+            if let Some(pos) = raw.find(token) {
+                let (left, right) = raw.split_at(pos);
+                let right = &right[token.len()..];
+                return Ok(FilterExpr {
+                    path: parse_relative_path(left)?,
+                    op,
+                    literal: parse_literal(right)?,
+                });
+            }
+        }
+        Err(JsonPathError(format!("filter expression '{}' has no comparison operator", raw)))
+    }
+
+    fn parse_bracket_content(content: &str) -> Result<Selector, JsonPathError> {
+        let content = content.trim();
+        if content == "*" {
+            return Ok(Selector::Wildcard);
+        }
+        if content.starts_with('?') {
+            return Ok(Selector::Filter(parse_filter(content)?));
+        }
+        if content.contains(':') {
+            let parts: Vec<&str> = content.split(':').collect();
+            let parse_part = |s: &str| -> Result<Option<i64>, JsonPathError> {
+                let s = s.trim();
+                if s.is_empty() {
+                    Ok(None)
+                } else {
+                    s.parse::<i64>()
+                        .map(Some)
+                        .map_err(|_| JsonPathError(format!("invalid slice bound '{}'", s)))
+                }
+            };
+            let start = parts.first().map(|s| parse_part(s)).transpose()?.flatten();
+            let end = parts.get(1).map(|s| parse_part(s)).transpose()?.flatten();
+            let step = parts.get(2).map(|s| parse_part(s)).transpose()?.flatten();
+            return Ok(Selector::Slice(start, end, step));
+        }
+        if content.starts_with('\'') || content.starts_with('"') {
+            let names: Vec<Selector> = content
+                .split(',')
+                .map(|s| Selector::Child(s.trim().trim_matches(|ch| ch == '\'' || ch == '"').to_string()))
+                .collect();
+            return Ok(if names.len() == 1 {
+                names.into_iter().next().unwrap()
+            } else {
+                Selector::Union(names)
+            });
+        }
+        let indices: Vec<Selector> = content
+            .split(',')
+            .map(|s| {
+                let s = s.trim();
+                s.parse::<i64>()
+                    .map(Selector::Index)
+                    .map_err(|_| JsonPathError(format!("invalid array index '{}' in jsonpath", s)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(if indices.len() == 1 {
+            indices.into_iter().next().unwrap()
+        } else {
+            Selector::Union(indices)
+        })
+    }
+
+    pub fn parse_path(path: &str) -> Result<Vec<Selector>, JsonPathError> {
+        let mut chars = path.chars().peekable();
+        if chars.next() != Some('$') {
+            return Err(JsonPathError("jsonpath expression must start with '$'".to_string()));
+        }
+
+        let mut selectors = Vec::new();
+        while let Some(&c) = chars.peek() {
+            match c {
+                '.' => {
+                    chars.next();
+                    if chars.peek() == Some(&'.') {
+                        chars.next();
+                        selectors.push(Selector::RecursiveDescent);
+                        if chars.peek() == Some(&'*') {
+                            chars.next();
+                            selectors.push(Selector::Wildcard);
+                        } else if chars.peek().is_some_and(|&c| is_ident_char(c)) {
+                            selectors.push(Selector::Child(read_ident(&mut chars)?));
+                        }
+                    } else if chars.peek() == Some(&'*') {
+                        chars.next();
+                        selectors.push(Selector::Wildcard);
+                    } else {
+                        selectors.push(Selector::Child(read_ident(&mut chars)?));
+                    }
+                }
+                '[' => {
+                    chars.next();
+                    let content = read_bracket_content(&mut chars)?;
+                    selectors.push(parse_bracket_content(&content)?);
+                }
+                _ => return Err(JsonPathError(format!("unexpected char '{}' in jsonpath expression", c))),
+            }
+        }
+        Ok(selectors)
+    }
+
+    fn children_of<'a>(node: &NodeCtx<'a>) -> Vec<NodeCtx<'a>> {
+        match node {
+            NodeCtx::Root(json) => json.iter().map(|(_, v)| NodeCtx::Value(v)).collect(),
+            NodeCtx::Value(JsonDtype::Object(json)) => {
+                json.iter().map(|(_, v)| NodeCtx::Value(v)).collect()
+            }
+            NodeCtx::Value(JsonDtype::Array(arr)) => arr.iter().map(NodeCtx::Value).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn child_by_name<'a>(node: &NodeCtx<'a>, name: &str) -> Option<NodeCtx<'a>> {
+        match node {
+            NodeCtx::Root(json) => json.get(name).map(NodeCtx::Value),
+            NodeCtx::Value(JsonDtype::Object(json)) => json.get(name).map(NodeCtx::Value),
+            _ => None,
+        }
+    }
+
+    fn index_of<'a>(node: &NodeCtx<'a>, idx: i64) -> Option<NodeCtx<'a>> {
+        match node {
+            NodeCtx::Value(JsonDtype::Array(arr)) => {
+                let len = arr.len() as i64;
+                let real = if idx < 0 { len + idx } else { idx };
+                if real < 0 || real >= len {
+                    None
+                } else {
+                    Some(NodeCtx::Value(&arr[real as usize]))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn slice_of<'a>(
+        node: &NodeCtx<'a>,
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    ) -> Vec<NodeCtx<'a>> {
+        let arr = match node {
+            NodeCtx::Value(JsonDtype::Array(arr)) => arr,
+            _ => return Vec::new(),
+        };
+        let len = arr.len() as i64;
+        let step = step.unwrap_or(1);
+        if step == 0 || len == 0 {
+            return Vec::new();
+        }
+        let clamp = |v: i64| -> i64 {
+            let v = if v < 0 { len + v } else { v };
+            v.clamp(0, len)
+        };
+
+        let mut out = Vec::new();
+        if step > 0 {
+            let start = start.map(clamp).unwrap_or(0);
+            let end = end.map(clamp).unwrap_or(len);
+            let mut i = start;
+            while i < end {
+                out.push(NodeCtx::Value(&arr[i as usize]));
+                i += step;
+            }
+        } else {
+            let start = start.map(clamp).unwrap_or(len - 1).min(len - 1);
+            let end = end.map(clamp).unwrap_or(-1);
+            let mut i = start;
+            while i > end {
+                if i < len {
+                    out.push(NodeCtx::Value(&arr[i as usize]));
+                }
+                i += step;
+            }
+        }
+        out
+    }
+
+    fn num_as_f64(n: &Num) -> f64 {
+        match n {
+            Num::Integer(i) => *i as f64,
+            Num::Unsigned(u) => *u as f64,
+            Num::Float(f) => *f,
+        }
+    }
+
+    fn compare(value: &JsonDtype, op: CmpOp, literal: &JsonDtype) -> bool {
+        match op {
+            CmpOp::Eq => value == literal,
+            CmpOp::Ne => value != literal,
+            _ => match (value, literal) {
+                (JsonDtype::Number(a), JsonDtype::Number(b)) => {
+                    let (a, b) = (num_as_f64(a), num_as_f64(b));
+                    match op {
+                        CmpOp::Lt => a < b,
+                        CmpOp::Le => a <= b,
+                        CmpOp::Gt => a > b,
+                        CmpOp::Ge => a >= b,
+                        CmpOp::Eq | CmpOp::Ne => unreachable!(),
+                    }
+                }
+                _ => false,
+            },
+        }
+    }
+
+    fn matches_filter(node: &NodeCtx, filter: &FilterExpr) -> bool {
+        let mut current = Some(*node);
+        for seg in &filter.path {
+            current = current.as_ref().and_then(|n| match seg {
+                PathSeg::Name(name) => child_by_name(n, name),
+                PathSeg::Index(idx) => index_of(n, *idx),
+            });
+        }
+        match current {
+            Some(NodeCtx::Value(v)) => compare(v, filter.op, &filter.literal),
+            _ => false,
+        }
+    }
+
+    fn apply_selector<'a>(nodes: &[NodeCtx<'a>], selector: &Selector) -> Vec<NodeCtx<'a>> {
+        match selector {
+            Selector::Child(name) => nodes.iter().filter_map(|n| child_by_name(n, name)).collect(),
+            Selector::Wildcard => nodes.iter().flat_map(children_of).collect(),
+            Selector::RecursiveDescent => {
+                let mut out = Vec::new();
+                for node in nodes {
+                    collect_descendants(node, &mut out);
+                }
+                out
+            }
+            Selector::Index(i) => nodes.iter().filter_map(|n| index_of(n, *i)).collect(),
+            Selector::Slice(start, end, step) => nodes
+                .iter()
+                .flat_map(|n| slice_of(n, *start, *end, *step))
+                .collect(),
+            Selector::Union(subs) => subs.iter().flat_map(|s| apply_selector(nodes, s)).collect(),
+            Selector::Filter(filter) => nodes
+                .iter()
+                .flat_map(|n| children_of(n).into_iter().filter(|c| matches_filter(c, filter)))
+                .collect(),
+        }
+    }
+
+    fn collect_descendants<'a>(node: &NodeCtx<'a>, out: &mut Vec<NodeCtx<'a>>) {
+        out.push(*node);
+        for child in children_of(node) {
+            collect_descendants(&child, out);
+        }
+    }
+
+    pub fn eval_path<'a>(root: &'a Json, selectors: &[Selector]) -> Vec<NodeCtx<'a>> {
+        let mut nodes = vec![NodeCtx::Root(root)];
+        for selector in selectors {
+            nodes = apply_selector(&nodes, selector);
+        }
+        nodes
+    }
+}
+
+/// The JSON type name of `value`, used in [`DecodeError::TypeMismatch`]
+/// messages. `pub` (not `pub(crate)`) because `json_codec!` is
+/// `#[macro_export]`ed and expands this call at the call site of whatever
+/// crate uses it, which needs the same visibility a real external caller
+/// would need.
+pub fn json_type_name(value: &JsonDtype) -> &'static str {
+    match value {
+        JsonDtype::String(_) => "string",
+        JsonDtype::Number(_) => "number",
+        JsonDtype::Object(_) => "object",
+        JsonDtype::Array(_) => "array",
+        JsonDtype::Boolean(_) => "boolean",
+        JsonDtype::Null => "null",
+    }
+}
+
+/// An error produced while decoding a [`JsonDtype`] into a typed Rust value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    MissingField(String),
+    TypeMismatch { expected: &'static str, found: String },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::MissingField(field) => write!(f, "missing field '{}'", field),
+            DecodeError::TypeMismatch { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Builds a [`JsonDtype`] from a typed Rust value.
+pub trait Encode {
+    fn encode(&self) -> JsonDtype;
+}
+
+/// Recovers a typed Rust value from a [`JsonDtype`].
+pub trait Decode: Sized {
+    fn decode(value: &JsonDtype) -> Result<Self, DecodeError>;
+
+    /// Called by [`Json::decode_field`] when `key` is absent from the
+    /// object, instead of `decode` (there's no `&JsonDtype` to decode).
+    /// Defaults to a `MissingField` error; `Option<T>` overrides this to
+    /// decode an absent key the same way it decodes an explicit `null`.
+    fn decode_missing(key: &str) -> Result<Self, DecodeError> {
+        Err(DecodeError::MissingField(key.to_string()))
+    }
+}
+
+impl Encode for i128 {
+    fn encode(&self) -> JsonDtype {
+        JsonDtype::Number(Num::Integer(*self))
+    }
+}
+
+impl Encode for f64 {
+    fn encode(&self) -> JsonDtype {
+        JsonDtype::Number(Num::Float(*self))
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self) -> JsonDtype {
+        JsonDtype::Boolean(*self)
+    }
+}
+
+impl Encode for String {
+    fn encode(&self) -> JsonDtype {
+        JsonDtype::String(self.clone())
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self) -> JsonDtype {
+        match self {
+            Some(value) => value.encode(),
+            None => JsonDtype::Null,
+        }
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self) -> JsonDtype {
+        JsonDtype::Array(self.iter().map(Encode::encode).collect())
+    }
+}
+
+impl Decode for i128 {
+    fn decode(value: &JsonDtype) -> Result<Self, DecodeError> {
+        match value {
+            JsonDtype::Number(Num::Integer(i)) => Ok(*i),
+            JsonDtype::Number(Num::Unsigned(u)) => Ok(*u as i128),
+            other => Err(DecodeError::TypeMismatch {
+                expected: "integer",
+                found: json_type_name(other).to_string(),
+            }),
+        }
+    }
+}
+
+impl Decode for f64 {
+    fn decode(value: &JsonDtype) -> Result<Self, DecodeError> {
+        match value {
+            JsonDtype::Number(Num::Float(f)) => Ok(*f),
+            JsonDtype::Number(Num::Integer(i)) => Ok(*i as f64),
+            JsonDtype::Number(Num::Unsigned(u)) => Ok(*u as f64),
+            other => Err(DecodeError::TypeMismatch {
+                expected: "number",
+                found: json_type_name(other).to_string(),
+            }),
+        }
+    }
+}
+
+impl Decode for bool {
+    fn decode(value: &JsonDtype) -> Result<Self, DecodeError> {
+        match value {
+            JsonDtype::Boolean(b) => Ok(*b),
+            other => Err(DecodeError::TypeMismatch {
+                expected: "boolean",
+                found: json_type_name(other).to_string(),
+            }),
+        }
+    }
+}
+
+impl Decode for String {
+    fn decode(value: &JsonDtype) -> Result<Self, DecodeError> {
+        match value {
+            JsonDtype::String(s) => Ok(s.clone()),
+            other => Err(DecodeError::TypeMismatch {
+                expected: "string",
+                found: json_type_name(other).to_string(),
+            }),
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(value: &JsonDtype) -> Result<Self, DecodeError> {
+        match value {
+            JsonDtype::Null => Ok(None),
+            other => T::decode(other).map(Some),
+        }
+    }
+
+    fn decode_missing(_key: &str) -> Result<Self, DecodeError> {
+        Ok(None)
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(value: &JsonDtype) -> Result<Self, DecodeError> {
+        match value {
+            JsonDtype::Array(arr) => arr.iter().map(T::decode).collect(),
+            other => Err(DecodeError::TypeMismatch {
+                expected: "array",
+                found: json_type_name(other).to_string(),
+            }),
+        }
+    }
+}
+
+impl Json {
+    /// Looks up `key` and decodes it via [`Decode::decode_missing`] if it's
+    /// absent (a [`DecodeError::MissingField`] by default, `None` for
+    /// `Option<T>`). Generated `Decode` impls pull each struct field this way.
+    pub fn decode_field<T: Decode>(&self, key: &str) -> Result<T, DecodeError> {
+        match self.get(key) {
+            Some(value) => T::decode(value),
+            None => T::decode_missing(key),
+        }
+    }
+
+    /// Decodes this whole object into a typed value, e.g. `let p: Person = json.decode()?;`.
+    pub fn decode<T: Decode>(&self) -> Result<T, DecodeError> {
+        T::decode(&JsonDtype::Object(self.clone()))
+    }
+}
+
+/// Maps a struct or enum field-for-field onto a [`Json`] value by generating
+/// `Encode`/`Decode` impls for it.
+///
+/// **Not the `#[derive(Encode, Decode)]` attribute macro the originating
+/// request asked for.** This crate is a single source file with no
+/// companion proc-macro crate, so a real derive (which needs its own
+/// `proc-macro = true` crate) isn't available here; `json_codec!` is a
+/// `macro_rules!` substitute chosen as the closest approximation this
+/// layout supports. That's a deliberate, structural trade-off rather than
+/// an oversight, but it is a different and more invasive API shape than a
+/// derive: a caller attaching to an already-declared item must re-list
+/// every field name and type a second time (see the `impl` form below)
+/// instead of the macro reading them off the item's own declaration.
+/// Flag this to whoever filed the request before merging, since it's a
+/// substitution they haven't signed off on. `macro_rules!` also can't
+/// attach to an item the way an attribute macro can, so this is invoked as
+/// its own item rather than placed on the struct:
+///
+/// ```ignore
+/// json_codec! {
+///     pub struct Person { name: String, age: i128 }
+/// }
+/// ```
+///
+/// which declares `Person` (with `pub` fields) *and* the impls in one shot.
+/// If the struct already exists — with its own derives, generics, or
+/// lifetimes the declare-form can't express — list its fields again under
+/// `impl` instead and only the trait impls are generated:
+///
+/// ```ignore
+/// #[derive(Debug, Clone)]
+/// pub struct Person { pub name: String, pub age: i128 }
+/// json_codec! { impl Person { name: String, age: i128 } }
+/// ```
+///
+/// Enums are supported the same way, variant-for-variant. A unit variant
+/// round-trips as a JSON string of its name; a variant with named fields
+/// round-trips as a single-key object, `{"VariantName": {field: value, ...}}`:
+///
+/// ```ignore
+/// json_codec! {
+///     pub enum Shape {
+///         Circle { radius: f64 },
+///         Point,
+///     }
+/// }
+/// ```
+///
+/// and, as with structs, an `impl enum` form attaches the impls to an
+/// already-declared enum instead of also declaring it.
+///
+/// The `impl` form also can't generate impls for generic or
+/// lifetime-parameterized structs/enums.
+#[macro_export]
+macro_rules! json_codec {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $( $field:ident : $ty:ty ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $( pub $field: $ty ),*
+        }
+
+        $crate::json_codec! { impl $name { $( $field : $ty ),* } }
+    };
+
+    (
+        impl $name:ident {
+            $( $field:ident : $ty:ty ),* $(,)?
+        }
+    ) => {
+        impl $crate::Encode for $name {
+            fn encode(&self) -> $crate::JsonDtype {
+                let mut obj = $crate::Json::new();
+                $( obj.insert(stringify!($field), $crate::Encode::encode(&self.$field)); )*
+                $crate::JsonDtype::Object(obj)
+            }
+        }
+
+        impl $crate::Decode for $name {
+            fn decode(value: &$crate::JsonDtype) -> Result<Self, $crate::DecodeError> {
+                match value {
+                    $crate::JsonDtype::Object(obj) => Ok($name {
+                        $( $field: obj.decode_field(stringify!($field))?, )*
+                    }),
+                    other => Err($crate::DecodeError::TypeMismatch {
+                        expected: "object",
+                        found: $crate::json_type_name(other).to_string(),
+                    }),
+                }
+            }
+        }
+    };
+
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $( $variant:ident $( { $( $vfield:ident : $vty:ty ),* $(,)? } )? ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $( $variant $( { $( $vfield: $vty ),* } )? ),*
+        }
+
+        $crate::json_codec! {
+            impl enum $name {
+                $( $variant $( { $( $vfield : $vty ),* } )? ),*
+            }
+        }
+    };
+
+    (
+        impl enum $name:ident {
+            $( $variant:ident $( { $( $vfield:ident : $vty:ty ),* $(,)? } )? ),* $(,)?
+        }
+    ) => {
+        impl $crate::Encode for $name {
+            fn encode(&self) -> $crate::JsonDtype {
+                match self {
+                    $(
+                        $crate::json_codec!(@pattern $name, $variant $( { $( $vfield ),* } )?) => {
+                            $crate::json_codec!(@encode_variant $variant $( { $( $vfield ),* } )?)
+                        }
+                    )*
+                }
+            }
+        }
+
+        impl $crate::Decode for $name {
+            fn decode(value: &$crate::JsonDtype) -> Result<Self, $crate::DecodeError> {
+                $(
+                    if let Some(decoded) = $crate::json_codec!(@try_decode_variant $name, value, $variant $( { $( $vfield : $vty ),* } )?)? {
+                        return Ok(decoded);
+                    }
+                )*
+                Err($crate::DecodeError::TypeMismatch {
+                    expected: stringify!($name),
+                    found: $crate::json_type_name(value).to_string(),
+                })
+            }
+        }
+    };
+
+    (@pattern $name:ident, $variant:ident) => {
+        $name::$variant
+    };
+    (@pattern $name:ident, $variant:ident { $( $vfield:ident ),* }) => {
+        $name::$variant { $( $vfield ),* }
+    };
+
+    (@encode_variant $variant:ident) => {
+        $crate::JsonDtype::String(stringify!($variant).to_string())
+    };
+    (@encode_variant $variant:ident { $( $vfield:ident ),* }) => {{
+        let mut fields = $crate::Json::new();
+        $( fields.insert(stringify!($vfield), $crate::Encode::encode($vfield)); )*
+        let mut obj = $crate::Json::new();
+        obj.insert(stringify!($variant), $crate::JsonDtype::Object(fields));
+        $crate::JsonDtype::Object(obj)
+    }};
+
+    (@try_decode_variant $name:ident, $value:expr, $variant:ident) => {
+        match $value {
+            $crate::JsonDtype::String(s) if s == stringify!($variant) => Ok(Some($name::$variant)),
+            _ => Ok(None),
+        }
+    };
+    (@try_decode_variant $name:ident, $value:expr, $variant:ident { $( $vfield:ident : $vty:ty ),* }) => {
+        match $value {
+            $crate::JsonDtype::Object(obj) if obj.len() == 1 && obj.get(stringify!($variant)).is_some() => {
+                let fields = obj.get(stringify!($variant)).unwrap();
+                match fields {
+                    $crate::JsonDtype::Object(fields) => Ok(Some($name::$variant {
+                        $( $vfield: fields.decode_field(stringify!($vfield))?, )*
+                    })),
+                    other => Err($crate::DecodeError::TypeMismatch {
+                        expected: "object",
+                        found: $crate::json_type_name(other).to_string(),
+                    }),
+                }
+            }
+            _ => Ok(None),
+        }
+    };
+}
+
+impl<K> Index<K> for Json
+where
+    K: Into<JsonDtype>,
+{
+    type Output = JsonDtype;
+
+    fn index(&self, index: K) -> &Self::Output {
+        self.map.get(&index.into()).expect("no entry found for key")
+    }
+}
+
+impl<K> IndexMut<K> for Json
+where
+    K: Into<JsonDtype>,
+{
+    fn index_mut(&mut self, index: K) -> &mut JsonDtype {
+        self.map.get_mut(&index.into()).expect("no entry found for key")
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, (key, value)) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", key, value)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+fn main() {
+    let mut json_obj = Json::parse(
+        r#"{"Hello": "World!", "potatoes": [1, 2, 3, { "a": 1 , "b": false, "c": null }],}"#,
+    )
+    .expect("failed to parse");
+    println!("{}", json_obj);
+
+    json_obj.insert("age", 20);
+    println!("{}", json_obj.stringify_pretty());
+
+    println!("{}", json_obj.get("Hello").unwrap());
+
+    json_obj.remove("Hello");
+    println!("{}", json_obj);
+
+    let mut json_obj2 = Json::new();
+    json_obj2.insert("age", 21);
+
+    println!("{}", json_obj2["age"]);
+    json_obj2["age"] = 22.into();
+    println!("{}", json_obj2["age"]);
+
+    json_obj.update(json_obj2);
+    println!("{}", json_obj);
+
+
+    let mut data_file = File::create("data.json").expect("creation failed");
+    json_obj.dumps_pretty(&mut data_file);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stringify_reparse_roundtrip_preserves_equality() {
+        let doc = Json::parse(
+            r#"{"name": "Ada", "age": 20, "big": 123456789012345, "pi": 3.5, "tags": ["x", "y"], "on": true, "off": null}"#,
+        )
+        .expect("failed to parse");
+
+        let reparsed = Json::parse(&doc.stringify()).expect("failed to reparse");
+        assert_eq!(doc, reparsed);
+
+        let reparsed_pretty = Json::parse(&doc.stringify_pretty()).expect("failed to reparse pretty output");
+        assert_eq!(doc, reparsed_pretty);
+    }
+
+    #[test]
+    fn integer_and_unsigned_compare_and_hash_equal() {
+        // `age` round-trips as an unsigned literal while the one constructed
+        // in-process is a plain (signed) integer; they must still compare
+        // and hash equal to keep parse/stringify round-trips lossless.
+        let parsed = Json::parse(r#"{"age": 20}"#).expect("failed to parse");
+        let mut built = Json::new();
+        built.insert("age", 20);
+        assert_eq!(parsed, built);
+
+        let mut set = std::collections::HashSet::new();
+        set.insert(parsed["age"].clone());
+        assert!(set.contains(&built["age"]));
+    }
+
+    #[test]
+    fn object_stringify_preserves_insertion_order() {
+        // The `map` is insertion-ordered, not hashed, so this order must be
+        // stable across repeated calls and must match insertion order even
+        // though none of the keys below would sort alphabetically that way.
+        let mut doc = Json::new();
+        doc.insert("zebra", 1);
+        doc.insert("apple", 2);
+        doc.insert("mango", 3);
+        assert_eq!(doc.stringify(), r#"{"zebra": 1, "apple": 2, "mango": 3}"#);
+        assert_eq!(doc.keys(), vec![
+            &JsonDtype::String("zebra".to_string()),
+            &JsonDtype::String("apple".to_string()),
+            &JsonDtype::String("mango".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn float_always_emits_a_decimal_point_so_it_does_not_reparse_as_an_integer() {
+        let mut doc = Json::new();
+        doc.insert("x", 2.0_f64);
+        let rendered = doc.stringify();
+        assert_eq!(rendered, r#"{"x": 2.0}"#);
+
+        let reparsed = Json::parse(&rendered).expect("failed to parse");
+        assert!(matches!(reparsed["x"], JsonDtype::Number(Num::Float(f)) if f == 2.0));
+    }
+
+    #[test]
+    fn leading_zero_integer_literals_are_rejected() {
+        assert!(Json::parse(r#"{"a": 0123}"#).is_err());
+        // A lone zero, and a zero fraction, are both still valid.
+        assert!(Json::parse(r#"{"a": 0}"#).is_ok());
+        assert!(Json::parse(r#"{"a": 0.5}"#).is_ok());
+    }
+
+    #[test]
+    fn array_root_is_wrapped_under_a_data_key() {
+        let doc = Json::parse("[1, 2, 3]").expect("array root should parse");
+        assert_eq!(doc.stringify(), "{\"data\": [1, 2, 3]}");
+    }
+
+    #[test]
+    fn scalar_root_is_rejected() {
+        let err = Json::parse(r#""just a string""#).expect_err("scalar root should not parse");
+        assert!(matches!(
+            err.kind,
+            ErrorKind::UnexpectedChar {
+                expected: "'{' or '['",
+                ..
+            }
+        ));
+        assert!(Json::parse("42").is_err());
+    }
+
+    #[test]
+    fn signed_exponents_parse_as_floats() {
+        let cases = [
+            (r#"{"x": 1e+10}"#, 1e10),
+            (r#"{"x": 1e-10}"#, 1e-10),
+            (r#"{"x": 1.5e+3}"#, 1.5e3),
+            (r#"{"x": -2E-5}"#, -2e-5),
+        ];
+        for (input, expected) in cases {
+            let doc = Json::parse(input).unwrap_or_else(|e| panic!("failed to parse {}: {:?}", input, e));
+            assert!(
+                matches!(doc["x"], JsonDtype::Number(Num::Float(f)) if f == expected),
+                "{} should parse as {}",
+                input,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn nan_and_infinity_are_excluded_from_stringify_output() {
+        let mut doc = Json::new();
+        doc.insert("x", f64::NAN);
+        assert_eq!(doc.stringify(), r#"{"x": null}"#);
+
+        let mut doc = Json::new();
+        doc.insert("x", f64::INFINITY);
+        assert_eq!(doc.stringify(), r#"{"x": null}"#);
+    }
+
+    #[test]
+    fn query_filter_matches_equal_and_greater_than() {
+        let doc = Json::parse(r#"{"items": [{"price": 5}, {"price": 10}]}"#).expect("failed to parse");
+
+        let eq_matches = doc.query("$.items[?(@.price==5)]").expect("valid jsonpath");
+        assert_eq!(eq_matches.len(), 1);
+
+        let gt_matches = doc.query("$.items[?(@.price>5)]").expect("valid jsonpath");
+        assert_eq!(gt_matches.len(), 1);
+    }
+
+    #[test]
+    fn query_returns_error_instead_of_panicking_on_malformed_path() {
+        let doc = Json::parse(r#"{"a": 1}"#).expect("failed to parse");
+        assert!(doc.query("not-a-path").is_err());
+    }
+
+    #[test]
+    fn bare_root_query_errors_on_borrowed_query_but_clones_via_query_owned() {
+        let doc = Json::parse(r#"{"a": 1}"#).expect("failed to parse");
+
+        assert!(doc.query("$").is_err());
+
+        let owned = doc.query_owned("$").expect("query_owned can return the root");
+        assert_eq!(owned, vec![JsonDtype::Object(doc.clone())]);
+    }
+
+    #[test]
+    fn query_recursive_descent_visits_object_values_and_array_elements() {
+        let doc = Json::parse(r#"{"a": {"id": 1}, "b": [{"id": 2}, {"id": 3}]}"#)
+            .expect("failed to parse");
+
+        let mut ids: Vec<i128> = doc
+            .query("$..id")
+            .expect("valid jsonpath")
+            .into_iter()
+            .map(|v| match v {
+                JsonDtype::Number(Num::Unsigned(u)) => *u as i128,
+                other => panic!("expected a number, got {:?}", other),
+            })
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn query_union_selects_each_listed_index() {
+        let doc = Json::parse(r#"{"items": [10, 20, 30]}"#).expect("failed to parse");
+        let matches = doc.query("$.items[0,2]").expect("valid jsonpath");
+        assert_eq!(
+            matches,
+            vec![
+                &JsonDtype::Number(Num::Unsigned(10)),
+                &JsonDtype::Number(Num::Unsigned(30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn query_bracket_child_selector_accepts_single_or_double_quoted_names() {
+        let doc = Json::parse(r#"{"name": "alice"}"#).expect("failed to parse");
+        assert_eq!(
+            doc.query("$['name']").expect("valid jsonpath"),
+            doc.query(r#"$["name"]"#).expect("valid jsonpath"),
+        );
+    }
+
+    #[test]
+    fn query_slice_clamps_out_of_range_and_supports_negative_bounds() {
+        let doc = Json::parse(r#"{"items": [0, 1, 2, 3]}"#).expect("failed to parse");
+
+        let last_two = doc.query("$.items[-2:]").expect("valid jsonpath");
+        assert_eq!(
+            last_two,
+            vec![
+                &JsonDtype::Number(Num::Unsigned(2)),
+                &JsonDtype::Number(Num::Unsigned(3)),
+            ]
+        );
+
+        let clamped_end = doc.query("$.items[0:100]").expect("valid jsonpath");
+        assert_eq!(clamped_end.len(), 4);
+
+        let out_of_range = doc.query("$.items[100:200]").expect("valid jsonpath");
+        assert!(out_of_range.is_empty());
+    }
+
+    #[test]
+    fn query_filter_on_missing_member_drops_the_node_instead_of_erroring() {
+        let doc = Json::parse(r#"{"items": [{"price": 5}, {"name": "no price"}]}"#)
+            .expect("failed to parse");
+        let matches = doc.query("$.items[?(@.price==5)]").expect("valid jsonpath");
+        assert_eq!(matches.len(), 1);
+    }
+
+    #[test]
+    fn query_filter_path_with_nested_bracket_index_does_not_terminate_early() {
+        // Regression test for the fix in 8ef9e09: a `]` that closes an inner
+        // index selector inside a filter's relative path (`@.tags[0]`) used
+        // to be mistaken for the end of the outer `[?(...)]` selector.
+        let doc = Json::parse(r#"{"items": [{"tags": ["a", "b"]}, {"tags": ["c"]}]}"#)
+            .expect("failed to parse");
+        let matches = doc
+            .query("$.items[?(@.tags[0]=='a')]")
+            .expect("nested bracket in filter path should parse");
+        assert_eq!(matches.len(), 1);
+    }
+
+    #[test]
+    fn parse_error_reports_line_and_column_of_the_bad_token() {
+        let err = Json::parse("{\n  \"a\": 1,\n  \"b\": tru\n}").expect_err("should reject truncated literal");
+        assert_eq!(err.line, 3);
+        assert_eq!(err.column, 11);
+        assert!(matches!(err.kind, ErrorKind::UnexpectedChar { found: 't', .. }));
+
+        let eof_err = Json::parse("{\"a\"").expect_err("should reject unterminated object");
+        assert_eq!(eof_err.line, 1);
+        assert!(matches!(eof_err.kind, ErrorKind::UnexpectedEof { .. }));
+    }
+
+    #[test]
+    fn string_escapes_round_trip_including_surrogate_pairs() {
+        let doc = Json::parse(r#"{"s": "tab\tctrl\u0000end"}"#)
+            .expect("failed to parse backslash-t and \\u00XX escapes");
+        assert_eq!(doc["s"], JsonDtype::String("tab\tctrl\u{0}end".to_string()));
+
+        // A surrogate pair for an astral-plane character (U+1F600 GRINNING FACE).
+        let emoji = Json::parse(r#"{"s": "😀"}"#).expect("failed to parse surrogate pair");
+        assert_eq!(emoji["s"], JsonDtype::String("\u{1F600}".to_string()));
+        assert_eq!(emoji.stringify(), r#"{"s": "😀"}"#);
+
+        // An unpaired high surrogate is invalid.
+        assert!(Json::parse(r#"{"s": "\ud83d"}"#).is_err());
+
+        // A raw control character (a literal newline) inside a string is
+        // rejected rather than silently accepted.
+        assert!(Json::parse("{\"s\": \"line1\nline2\"}").is_err());
+    }
+
+    #[test]
+    fn json_codec_round_trips_and_reports_missing_and_mismatched_fields() {
+        json_codec! {
+            #[derive(Debug)]
+            struct Pet {
+                name: String,
+                age: i128,
+                nickname: Option<String>,
+            }
+        }
+
+        let doc = Json::parse(r#"{"name": "Rex", "age": 3}"#).expect("failed to parse");
+        let pet: Pet = doc.decode().expect("missing Option field should decode as None");
+        assert_eq!(pet.name, "Rex");
+        assert_eq!(pet.age, 3);
+        assert_eq!(pet.nickname, None);
+
+        let encoded = pet.encode();
+        let JsonDtype::Object(reencoded) = &encoded else {
+            panic!("encode() should produce an object");
+        };
+        let round_tripped: Pet = reencoded.decode().expect("re-decoding the encoded object should succeed");
+        assert_eq!(round_tripped.name, "Rex");
+
+        let missing_name = Json::parse(r#"{"age": 3}"#).expect("failed to parse");
+        assert_eq!(
+            missing_name.decode::<Pet>().unwrap_err(),
+            DecodeError::MissingField("name".to_string())
+        );
+
+        let wrong_type = Json::parse(r#"{"name": "Rex", "age": "three"}"#).expect("failed to parse");
+        assert_eq!(
+            wrong_type.decode::<Pet>().unwrap_err(),
+            DecodeError::TypeMismatch { expected: "integer", found: "string".to_string() }
+        );
+    }
+
+    #[test]
+    fn json_codec_round_trips_unit_and_struct_like_enum_variants() {
+        json_codec! {
+            #[derive(Debug, PartialEq)]
+            enum Shape {
+                Circle { radius: f64 },
+                Point,
+            }
+        }
+
+        let circle = Shape::Circle { radius: 2.5 };
+        let encoded = circle.encode();
+        let mut expected_fields = Json::new();
+        expected_fields.insert("radius", 2.5);
+        let mut expected = Json::new();
+        expected.insert("Circle", JsonDtype::Object(expected_fields));
+        assert_eq!(encoded, JsonDtype::Object(expected));
+        let decoded = Shape::decode(&encoded).expect("failed to decode Circle variant");
+        assert_eq!(decoded, circle);
+
+        let point = Shape::Point;
+        assert_eq!(point.encode(), JsonDtype::String("Point".to_string()));
+        let decoded_point = Shape::decode(&point.encode()).expect("failed to decode Point variant");
+        assert_eq!(decoded_point, point);
+
+        let bad = JsonDtype::String("NotAVariant".to_string());
+        assert!(Shape::decode(&bad).is_err());
+    }
+
+    #[test]
+    fn streaming_parser_emits_events_in_document_order() {
+        let parser = Parser::new(r#"{"a": [1, "x"], "b": null}"#.chars());
+        let events: Vec<JsonEvent> = parser.collect::<ParseResult<Vec<_>>>().expect("valid document");
+
+        assert_eq!(
+            events,
+            vec![
+                JsonEvent::ObjectStart,
+                JsonEvent::Key("a".to_string()),
+                JsonEvent::ArrayStart,
+                JsonEvent::Unsigned(1),
+                JsonEvent::String("x".to_string()),
+                JsonEvent::ArrayEnd,
+                JsonEvent::Key("b".to_string()),
+                JsonEvent::Null,
+                JsonEvent::ObjectEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn streaming_parser_tracks_container_stack_and_reports_errors() {
+        let mut parser = Parser::new(r#"{"a": [1"#.chars());
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ObjectStart)));
+        assert_eq!(parser.stack(), vec![StackElement::Key(String::new())]);
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::Key("a".to_string()))));
+        assert_eq!(parser.stack(), vec![StackElement::Key("a".to_string())]);
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ArrayStart)));
+        assert_eq!(
+            parser.stack(),
+            vec![StackElement::Key("a".to_string()), StackElement::Index(0)]
+        );
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::Unsigned(1))));
+        assert!(matches!(parser.next(), Some(Err(_))));
+    }
+
+    #[test]
+    fn streaming_parser_stack_reports_array_index_and_object_key_path() {
+        let mut parser = Parser::new(r#"{"a": [0, 1]}"#.chars());
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ObjectStart)));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::Key("a".to_string()))));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ArrayStart)));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::Unsigned(0))));
+        assert_eq!(
+            parser.stack(),
+            vec![StackElement::Key("a".to_string()), StackElement::Index(0)],
+            "first array element should be reported at index 0"
+        );
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::Unsigned(1))));
+        assert_eq!(
+            parser.stack(),
+            vec![StackElement::Key("a".to_string()), StackElement::Index(1)],
+            "second array element should be reported at index 1, distinguishing a[0] from a[1]"
+        );
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ArrayEnd)));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ObjectEnd)));
+    }
+
+    #[test]
+    fn streaming_parser_stack_index_does_not_double_increment_for_container_elements() {
+        // Regression test: closing a nested object/array element used to
+        // bump the parent array's index, and the following `,` bumped it a
+        // second time, so the second element was misreported as index 2.
+        let mut parser = Parser::new(r#"[{"a": 1}, {"b": 2}]"#.chars());
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ArrayStart)));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ObjectStart)));
+        assert_eq!(parser.stack()[0], StackElement::Index(0));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::Key("a".to_string()))));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::Unsigned(1))));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ObjectEnd)));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ObjectStart)));
+        assert_eq!(
+            parser.stack()[0],
+            StackElement::Index(1),
+            "second array element is itself an object, so the array index must still read 1, not 2"
+        );
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::Key("b".to_string()))));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::Unsigned(2))));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ObjectEnd)));
+        assert_eq!(parser.next(), Some(Ok(JsonEvent::ArrayEnd)));
+    }
+}